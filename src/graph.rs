@@ -1,8 +1,11 @@
+use std::char;
 use std::f64;
+use std::collections::HashMap;
 
 use BitMap;
 use line;
 use Axis;
+use image;
 
 const W_ARROW: usize = 4;      //width of arrow
 const W_NUMBER: usize = 4;     //number width in pixel
@@ -13,6 +16,16 @@ const H_ARROW_HALF: usize = 3;
 const LEFT_SHIFT: usize = W_BORDER + W_NUMBER + H_NUMBER;
 const RIGHT_SHIFT: usize = W_ARROW;
 
+const LEGEND_MARGIN: usize = 2;       //space inside the legend box
+const LEGEND_SWATCH: usize = 4;       //size of the color swatch square
+const LEGEND_GAP: usize = 2;          //gap between swatch and label
+const LEGEND_CHAR_W: usize = 3;       //label glyph width in pixels
+const LEGEND_CHAR_H: usize = 5;       //label glyph height in pixels
+const LEGEND_CHAR_GAP: usize = 1;     //gap between glyphs
+const LEGEND_LINE_GAP: usize = 1;     //gap between legend rows
+
+const BAR_GAP: usize = 1;             //space left between adjacent bars
+
 
 quick_error! {
     #[derive(Debug)]
@@ -26,11 +39,214 @@ quick_error! {
         NonUniquePoints {
             description("There are only one unique point. Can't construct line.")
         }
+        NonPositiveLogValue {
+            description("Logarithmic axes require values strictly greater than zero.")
+        }
     }
 }
 
 pub type GraphResult = Result<Vec<u8>, GraphError>;
 
+// pixel sink a Chart renders into; owns the final encoding (BMP, PNG, ...)
+pub trait Backend {
+    fn new(width: usize, height: usize) -> Self;
+    fn set_pixel(&mut self, x: usize, y: usize, rgb: (u8, u8, u8));
+    fn dimensions(&self) -> (usize, usize);
+    fn encode(&mut self) -> Vec<u8>;
+
+    // lets backends like BrailleBackend tell a painted pixel from background
+    fn set_background(&mut self, _rgb: (u8, u8, u8)) {}
+
+    // axis/grid color, for backends that render axis lines distinctly
+    fn set_axis_color(&mut self, _rgb: (u8, u8, u8)) {}
+}
+
+fn parse_color(color: &str) -> (u8, u8, u8) {
+    let hex = color.trim_left_matches('#');
+    let r = u8::from_str_radix(&hex[0..2], 16).unwrap_or(0);
+    let g = u8::from_str_radix(&hex[2..4], 16).unwrap_or(0);
+    let b = u8::from_str_radix(&hex[4..6], 16).unwrap_or(0);
+    (r, g, b)
+}
+
+fn to_hex_color(rgb: (u8, u8, u8)) -> String {
+    format!("#{:02x}{:02x}{:02x}", rgb.0, rgb.1, rgb.2)
+}
+
+// palette-indexed BMP output, limited to 256 colors
+pub struct BmpBackend {
+    width: usize,
+    height: usize,
+    picture: BitMap,
+    palette: HashMap<(u8, u8, u8), u8>,
+    indices: Vec<u8>,
+}
+
+impl Backend for BmpBackend {
+    fn new(width: usize, height: usize) -> Self {
+        BmpBackend {
+            width: width,
+            height: height,
+            picture: BitMap::new(width, height),
+            palette: HashMap::new(),
+            indices: vec![0; width * height],
+        }
+    }
+
+    fn set_pixel(&mut self, x: usize, y: usize, rgb: (u8, u8, u8)) {
+        let picture = &mut self.picture;
+        let index = *self.palette
+            .entry(rgb)
+            .or_insert_with(|| picture.add_color(&to_hex_color(rgb)));
+        self.indices[y * self.width + x] = index;
+    }
+
+    fn dimensions(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    fn encode(&mut self) -> Vec<u8> {
+        self.picture.add_pixels(&self.indices);
+        self.picture.to_vec()
+    }
+}
+
+// true-color PNG output via the image crate, no palette ceiling
+pub struct PngBackend {
+    image: image::RgbaImage,
+}
+
+impl Backend for PngBackend {
+    fn new(width: usize, height: usize) -> Self {
+        PngBackend { image: image::RgbaImage::new(width as u32, height as u32) }
+    }
+
+    fn set_pixel(&mut self, x: usize, y: usize, rgb: (u8, u8, u8)) {
+        self.image.put_pixel(x as u32, y as u32, image::Rgba([rgb.0, rgb.1, rgb.2, 255]));
+    }
+
+    fn dimensions(&self) -> (usize, usize) {
+        (self.image.width() as usize, self.image.height() as usize)
+    }
+
+    fn encode(&mut self) -> Vec<u8> {
+        let mut buf = vec![];
+        image::png::PNGEncoder::new(&mut buf)
+            .encode(&self.image, self.image.width(), self.image.height(), image::ColorType::RGBA(8))
+            .expect("failed to encode PNG");
+        buf
+    }
+}
+
+// dot position -> U+2800 bitmask bit
+fn braille_bit(row: usize, col: usize) -> u8 {
+    match (row, col) {
+        (0, 0) => 0,
+        (1, 0) => 1,
+        (2, 0) => 2,
+        (0, 1) => 3,
+        (1, 1) => 4,
+        (2, 1) => 5,
+        (3, 0) => 6,
+        (3, 1) => 7,
+        _ => unreachable!(),
+    }
+}
+
+// rasterizes into 2x4-dot Braille cells for terminal/SSH output
+pub struct BrailleBackend {
+    width: usize,
+    height: usize,
+    background: (u8, u8, u8),
+    axis_color: (u8, u8, u8),
+    dots: Vec<Option<(u8, u8, u8)>>,
+}
+
+impl BrailleBackend {
+    fn cell_char(&self, cell_x: usize, cell_y: usize) -> char {
+        let mut rows = vec![];
+        let mut cols = vec![];
+        let mut mask = 0u8;
+        let mut has_non_axis = false;
+
+        for row in 0..4 {
+            for col in 0..2 {
+                let x = cell_x * 2 + col;
+                let y = cell_y * 4 + row;
+                if x >= self.width || y >= self.height {
+                    continue;
+                }
+                if let Some(color) = self.dots[y * self.width + x] {
+                    mask |= 1 << braille_bit(row, col);
+                    rows.push(row);
+                    cols.push(col);
+                    if color != self.axis_color {
+                        has_non_axis = true;
+                    }
+                }
+            }
+        }
+
+        if mask == 0 {
+            return ' ';
+        }
+
+        if has_non_axis {
+            return char::from_u32(0x2800 + mask as u32).unwrap_or('?');
+        }
+
+        rows.dedup();
+        cols.dedup();
+        match (rows.len() > 1, cols.len() > 1) {
+            (true, false) => '|',
+            (false, true) => '-',
+            _ => '+',
+        }
+    }
+}
+
+impl Backend for BrailleBackend {
+    fn new(width: usize, height: usize) -> Self {
+        BrailleBackend {
+            width: width,
+            height: height,
+            background: (255, 255, 255),
+            axis_color: (0, 0, 0),
+            dots: vec![None; width * height],
+        }
+    }
+
+    fn set_background(&mut self, rgb: (u8, u8, u8)) {
+        self.background = rgb;
+    }
+
+    fn set_axis_color(&mut self, rgb: (u8, u8, u8)) {
+        self.axis_color = rgb;
+    }
+
+    fn set_pixel(&mut self, x: usize, y: usize, rgb: (u8, u8, u8)) {
+        self.dots[y * self.width + x] = if rgb == self.background { None } else { Some(rgb) };
+    }
+
+    fn dimensions(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    fn encode(&mut self) -> Vec<u8> {
+        let cell_width = (self.width + 1) / 2;
+        let cell_height = (self.height + 3) / 4;
+
+        let mut out = String::with_capacity((cell_width + 1) * cell_height);
+        for cell_y in 0..cell_height {
+            for cell_x in 0..cell_width {
+                out.push(self.cell_char(cell_x, cell_y));
+            }
+            out.push('\n');
+        }
+        out.into_bytes()
+    }
+}
+
 
 #[derive(Clone, Copy)]
 pub struct Point {
@@ -57,6 +273,35 @@ pub struct DisplayPoint {
 }
 
 
+// how a Serie's points get turned into pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChartKind {
+    // connects points with a polyline (original behavior)
+    Line,
+    // filled bar from the x-axis baseline to y
+    Bar,
+    // bins the points' x values and draws a bar per bin
+    Histogram { bins: usize },
+    // stamps a marker glyph at each point instead of connecting them
+    Scatter { marker: Marker, radius: usize },
+}
+
+// marker glyph for ChartKind::Scatter
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Marker {
+    Dot,
+    Cross,
+    Square,
+    X,
+}
+
+// how axis values map to pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scale {
+    Linear,
+    Log10,
+}
+
 #[derive(Debug)]
 pub struct Serie<'a, T, P>
     where T: Iterator<Item = P> + Clone,
@@ -64,6 +309,8 @@ pub struct Serie<'a, T, P>
 {
     pub iter: T,
     color: &'a str,
+    label: &'a str,
+    kind: ChartKind,
     max_x: f64,
     max_y: f64,
     min_x: f64,
@@ -74,7 +321,7 @@ impl<'a, T, P> Serie<'a, T, P>
     where T: Iterator<Item = P> + Clone,
           P: Into<Point> + PartialEq
 {
-    pub fn new(iter: T, color: &'a str) -> Result<Self, GraphError> {
+    pub fn new(iter: T, color: &'a str, label: &'a str) -> Result<Self, GraphError> {
 
         if iter.clone().nth(1).is_none() {
             return Err(GraphError::NotEnoughPoints);
@@ -90,6 +337,8 @@ impl<'a, T, P> Serie<'a, T, P>
         Ok(Serie {
             iter: iter,
             color: color,
+            label: label,
+            kind: ChartKind::Line,
             max_x: max_x,
             max_y: max_y,
             min_x: min_x,
@@ -97,6 +346,54 @@ impl<'a, T, P> Serie<'a, T, P>
         })
     }
 
+    pub fn with_kind(mut self, kind: ChartKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    // shared axis range this serie contributes; a histogram contributes bin counts, not raw y
+    fn axis_range(&self) -> (f64, f64, f64, f64) {
+        match self.kind {
+            ChartKind::Histogram { bins } => {
+                let max_count = self.histogram_counts(bins).into_iter().max().unwrap_or(0);
+                (self.max_x, self.min_x, max_count as f64, 0f64)
+            }
+            ChartKind::Line | ChartKind::Bar | ChartKind::Scatter { .. } =>
+                (self.max_x, self.min_x, self.max_y, self.min_y),
+        }
+    }
+
+    fn histogram_counts(&self, bins: usize) -> Vec<usize> {
+        let bins = bins.max(1);
+        let bin_width = (self.max_x - self.min_x) / (bins as f64);
+        let mut counts = vec![0usize; bins];
+        for p in self.iter.clone() {
+            let x = p.into().x;
+            let mut idx = if bin_width > 0f64 {
+                ((x - self.min_x) / bin_width) as usize
+            } else {
+                0
+            };
+            if idx >= bins {
+                idx = bins - 1;
+            }
+            counts[idx] += 1;
+        }
+        counts
+    }
+
+    // smallest gap between distinct x values, so bars don't overlap
+    fn min_x_spacing(&self) -> f64 {
+        let mut xs: Vec<f64> = self.iter.clone().map(|p| p.into().x).collect();
+        xs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        xs.dedup();
+        if xs.len() > 1 {
+            xs.windows(2).map(|w| w[1] - w[0]).fold(f64::INFINITY, f64::min)
+        } else {
+            self.max_x - self.min_x
+        }
+    }
+
     fn calculate_max_min(iter: T) -> (f64, f64, f64, f64) {
         let (mut min_x, mut max_x) = (f64::INFINITY, f64::NEG_INFINITY);
         let (mut min_y, mut max_y) = (f64::INFINITY, f64::NEG_INFINITY);
@@ -121,16 +418,34 @@ impl<'a, T, P> Serie<'a, T, P>
 }
 
 
+// log10 of the decade boundaries (..., 0.1, 1, 10, ...) and their 2-9 minor
+// subdivisions that fall within [min_log, max_log]
+fn log_decade_ticks(min_log: f64, max_log: f64) -> Vec<f64> {
+    let start_decade = min_log.floor() as i32 - 1;
+    let end_decade = max_log.ceil() as i32 + 1;
+    let mut ticks = vec![];
+    for e in start_decade..=end_decade {
+        for d in 1..=9 {
+            let log_value = (d as f64).log10() + (e as f64);
+            if log_value >= min_log - 1e-9 && log_value <= max_log + 1e-9 {
+                ticks.push(log_value);
+            }
+        }
+    }
+    ticks
+}
+
 #[derive(Debug)]
 pub struct Chart {
     width: usize,
     height: usize,
-    background_color: u8,
-    axis_color: u8,
-    pixs: Vec<u8>,
-    picture: BitMap,
+    background_color: (u8, u8, u8),
+    axis_color: (u8, u8, u8),
+    pixs: Vec<(u8, u8, u8)>,
     axis_x: Option<Axis>,
     axis_y: Option<Axis>,
+    scale_x: Scale,
+    scale_y: Scale,
 }
 
 impl Chart {
@@ -145,35 +460,75 @@ impl Chart {
             return Err(GraphError::NotEnoughSpace);
         };
 
-        let mut picture = BitMap::new(width, height);
-
-        let background_color_number = picture.add_color(background_color);
+        let background_color = parse_color(background_color);
 
-        let axis_color_number = picture.add_color(axis_color);
+        let axis_color = parse_color(axis_color);
 
         let size = width * height;
 
-        let pixs = vec![background_color_number;  size];
+        let pixs = vec![background_color; size];
 
         Ok(Chart {
             width: width,
             height: height,
-            background_color: background_color_number,
-            axis_color: axis_color_number,
+            background_color: background_color,
+            axis_color: axis_color,
             pixs: pixs,
-            picture: picture,
             axis_x: None,
             axis_y: None,
+            scale_x: Scale::Linear,
+            scale_y: Scale::Linear,
         })
     }
 
-    fn draw_axes<'a, T, P>(&mut self, serie: &Serie<'a, T, P>)
+    pub fn with_x_scale(mut self, scale: Scale) -> Self {
+        self.scale_x = scale;
+        self
+    }
+
+    pub fn with_y_scale(mut self, scale: Scale) -> Self {
+        self.scale_y = scale;
+        self
+    }
+
+    fn transform_x(&self, x: f64) -> f64 {
+        match self.scale_x {
+            Scale::Linear => x,
+            Scale::Log10 => x.log10(),
+        }
+    }
+
+    fn transform_y(&self, y: f64) -> f64 {
+        match self.scale_y {
+            Scale::Linear => y,
+            Scale::Log10 => y.log10(),
+        }
+    }
+
+    fn draw_axes<'a, T, P>(&mut self, series: &[Serie<'a, T, P>]) -> Result<(), GraphError>
         where T: Iterator<Item = P> + Clone,
               P: Into<Point> + PartialEq
     {
-        let axis_x = Axis::calculate_axis(serie.max_x, serie.min_x, self.width);
+        let ranges: Vec<(f64, f64, f64, f64)> = series.iter().map(|s| s.axis_range()).collect();
+
+        let max_x = ranges.iter().fold(f64::NEG_INFINITY, |acc, r| acc.max(r.0));
+        let min_x = ranges.iter().fold(f64::INFINITY, |acc, r| acc.min(r.1));
+        let max_y = ranges.iter().fold(f64::NEG_INFINITY, |acc, r| acc.max(r.2));
+        let min_y = ranges.iter().fold(f64::INFINITY, |acc, r| acc.min(r.3));
+
+        if (self.scale_x == Scale::Log10 && min_x <= 0f64) ||
+           (self.scale_y == Scale::Log10 && min_y <= 0f64) {
+            return Err(GraphError::NonPositiveLogValue);
+        }
+
+        // Axis is always built from a linear range; for Scale::Log10 that
+        // range is the log10 of the data. get_minor_net special-cases
+        // Scale::Log10 to snap gridlines to decade boundaries instead of
+        // spacing them evenly in that log-space range.
+        let axis_x = Axis::calculate_axis(self.transform_x(max_x), self.transform_x(min_x), self.width);
 
-        let axis_y = Axis::calculate_axis(serie.max_y, serie.min_y, self.height).rotate();
+        let axis_y = Axis::calculate_axis(self.transform_y(max_y), self.transform_y(min_y), self.height)
+            .rotate();
 
         let minor_net = self.get_minor_net(&axis_x, &axis_y);
 
@@ -188,60 +543,128 @@ impl Chart {
         self.axis_x = Some(axis_x);
 
         self.axis_y = Some(axis_y);
+
+        Ok(())
     }
 
     fn get_minor_net(&self, axis_x: &Axis, axis_y: &Axis) -> Vec<DisplayPoint> {
         let mut v: Vec<DisplayPoint> = vec![];
-        for i in 0..axis_x.k_i {
-            let shift = LEFT_SHIFT + ((axis_x.c_i * (i as f64)).round() as usize);
-            for j in LEFT_SHIFT..(self.height - H_ARROW_HALF) {
-                if j % 2 != 0 {
-                    v.push(DisplayPoint { x: shift, y: j });
+
+        match self.scale_x {
+            Scale::Linear => {
+                for i in 0..axis_x.k_i {
+                    let shift = LEFT_SHIFT + ((axis_x.c_i * (i as f64)).round() as usize);
+                    for j in LEFT_SHIFT..(self.height - H_ARROW_HALF) {
+                        if j % 2 != 0 {
+                            v.push(DisplayPoint { x: shift, y: j });
+                        }
+                    }
+                }
+            }
+            Scale::Log10 => {
+                for log_value in log_decade_ticks(axis_x.min_value, axis_x.max_value) {
+                    let shift = self.axis_pos(axis_x, self.width, log_value);
+                    for j in LEFT_SHIFT..(self.height - H_ARROW_HALF) {
+                        if j % 2 != 0 {
+                            v.push(DisplayPoint { x: shift, y: j });
+                        }
+                    }
                 }
             }
         }
 
-        for i in 0..axis_y.k_i {
-            let shift = LEFT_SHIFT + ((axis_y.c_i * (i as f64)).round() as usize);
-            for j in LEFT_SHIFT..(self.width - H_ARROW_HALF) {
-                if j % 2 != 0 {
-                    v.push(DisplayPoint { x: j, y: shift });
+        match self.scale_y {
+            Scale::Linear => {
+                for i in 0..axis_y.k_i {
+                    let shift = LEFT_SHIFT + ((axis_y.c_i * (i as f64)).round() as usize);
+                    for j in LEFT_SHIFT..(self.width - H_ARROW_HALF) {
+                        if j % 2 != 0 {
+                            v.push(DisplayPoint { x: j, y: shift });
+                        }
+                    }
+                }
+            }
+            Scale::Log10 => {
+                for log_value in log_decade_ticks(axis_y.min_value, axis_y.max_value) {
+                    let shift = self.axis_pos(axis_y, self.height, log_value);
+                    for j in LEFT_SHIFT..(self.width - H_ARROW_HALF) {
+                        if j % 2 != 0 {
+                            v.push(DisplayPoint { x: j, y: shift });
+                        }
+                    }
                 }
             }
         }
+
         v
     }
 
+    // pixel offset of a value already in axis space, along an axis covering `extent` pixels
+    fn axis_pos(&self, axis: &Axis, extent: usize, transformed_value: f64) -> usize {
+        let available = extent - LEFT_SHIFT - RIGHT_SHIFT;
+        let resolution = (axis.max_value - axis.min_value) / (available as f64);
+        let mut id = ((transformed_value - axis.min_value) / resolution).round() as usize;
+        if id >= available {
+            id = available - 1;
+        }
+        id + LEFT_SHIFT
+    }
 
 
 
-    pub fn create_bmp_vec<'a, T, P>(&mut self, serie: Serie<'a, T, P>) -> GraphResult
-        where T: Iterator<Item = P> + Clone,
+
+    pub fn render<'a, B, T, P>(&mut self, series: Vec<Serie<'a, T, P>>) -> GraphResult
+        where B: Backend,
+              T: Iterator<Item = P> + Clone,
               P: Into<Point> + PartialEq
     {
 
-        self.draw_axes(&serie);
+        if series.is_empty() {
+            return Err(GraphError::NotEnoughPoints);
+        }
 
-        let func_points = {
+        self.draw_axes(&series)?;
 
-            let function = self.serie_to_points(&serie);
+        let mut legend_entries: Vec<((u8, u8, u8), &'a str)> = Vec::with_capacity(series.len());
 
-            line::extrapolate(function).collect::<Vec<DisplayPoint>>()
+        for serie in &series {
 
-        };
+            let func_points = match serie.kind {
+                ChartKind::Line => {
+                    let function = self.serie_to_points(serie);
+                    line::extrapolate(function).collect::<Vec<DisplayPoint>>()
+                }
+                ChartKind::Bar => self.bar_points(serie),
+                ChartKind::Histogram { bins } => self.histogram_points(serie, bins),
+                ChartKind::Scatter { marker, radius } => self.scatter_points(serie, marker, radius),
+            };
 
-        let points_color_number = self.picture.add_color(serie.color);
+            let color = parse_color(serie.color);
 
-        self.draw_pixels(func_points, points_color_number);
+            self.draw_pixels(func_points, color);
 
-        self.picture.add_pixels(&self.pixs);
+            legend_entries.push((color, serie.label));
+        }
+
+        self.draw_legend(&legend_entries);
+
+        let mut backend = B::new(self.width, self.height);
+
+        backend.set_background(self.background_color);
+        backend.set_axis_color(self.axis_color);
 
-        Ok(self.picture.to_vec())
+        for y in 0..self.height {
+            for x in 0..self.width {
+                backend.set_pixel(x, y, self.pixs[y * self.width + x]);
+            }
+        }
+
+        Ok(backend.encode())
     }
 
-    fn serie_to_points<'a, T, P>(&'a mut self,
-                                 serie: &'a Serie<'a, T, P>)
-                                 -> Box<Iterator<Item = DisplayPoint> + 'a>
+    fn serie_to_points<'s, 'a, T, P>(&'s self,
+                                     serie: &'s Serie<'a, T, P>)
+                                     -> Box<Iterator<Item = DisplayPoint> + 's>
         where T: Iterator<Item = P> + Clone,
               P: Into<Point> + PartialEq
     {
@@ -256,12 +679,22 @@ impl Chart {
         let resolution_x: f64 = (axis_x.max_value - axis_x.min_value) / (width_available as f64);
         let resolution_y: f64 = (axis_y.max_value - axis_y.min_value) / (height_available as f64);
 
+        let scale_x = self.scale_x;
+        let scale_y = self.scale_y;
         let serie_iter = serie.iter.clone();
 
         Box::new(serie_iter.map(move |p| {
             let p = p.into();
-            let mut id_x = ((p.x - axis_x.min_value) / resolution_x).round() as usize;
-            let mut id_y = ((p.y - axis_y.min_value) / resolution_y).round() as usize;
+            let x = match scale_x {
+                Scale::Linear => p.x,
+                Scale::Log10 => p.x.log10(),
+            };
+            let y = match scale_y {
+                Scale::Linear => p.y,
+                Scale::Log10 => p.y.log10(),
+            };
+            let mut id_x = ((x - axis_x.min_value) / resolution_x).round() as usize;
+            let mut id_y = ((y - axis_y.min_value) / resolution_y).round() as usize;
 
             if id_x == self.width {
                 id_x -= 1;
@@ -278,12 +711,302 @@ impl Chart {
     }
 
 
-    fn draw_pixels(&mut self, points: Vec<DisplayPoint>, color: u8) {
+    // maps an already-transformed x value to a pixel column
+    fn map_x_raw(&self, transformed_x: f64) -> usize {
+        let width_available = self.width - LEFT_SHIFT - RIGHT_SHIFT;
+        let axis_x = self.axis_x.clone().unwrap();
+        let resolution_x = (axis_x.max_value - axis_x.min_value) / (width_available as f64);
+        let mut id_x = ((transformed_x - axis_x.min_value) / resolution_x).round() as usize;
+        if id_x == self.width {
+            id_x -= 1;
+        }
+        id_x + LEFT_SHIFT
+    }
+
+    // maps an already-transformed y value to a pixel row
+    fn map_y_raw(&self, transformed_y: f64) -> usize {
+        let height_available = self.height - LEFT_SHIFT - RIGHT_SHIFT;
+        let axis_y = self.axis_y.clone().unwrap();
+        let resolution_y = (axis_y.max_value - axis_y.min_value) / (height_available as f64);
+        let mut id_y = ((transformed_y - axis_y.min_value) / resolution_y).round() as usize;
+        if id_y == self.height {
+            id_y -= 1;
+        }
+        id_y + LEFT_SHIFT
+    }
+
+    fn map_x(&self, x: f64) -> usize {
+        self.map_x_raw(self.transform_x(x))
+    }
+
+    fn map_y(&self, y: f64) -> usize {
+        self.map_y_raw(self.transform_y(y))
+    }
+
+    // x-axis baseline pixel row; log axis has no zero so it drops to the axis minimum
+    fn baseline_y(&self) -> usize {
+        let axis_y = self.axis_y.clone().unwrap();
+        let transformed_baseline = match self.scale_y {
+            Scale::Linear => {
+                if 0f64 < axis_y.min_value {
+                    axis_y.min_value
+                } else if 0f64 > axis_y.max_value {
+                    axis_y.max_value
+                } else {
+                    0f64
+                }
+            }
+            Scale::Log10 => axis_y.min_value,
+        };
+        self.map_y_raw(transformed_baseline)
+    }
+
+    // half-width in pixels of a bar centered on raw x `center_x` spanning `spacing_x` raw
+    // units in total (half of it either side); goes through map_x so it's correct under Scale::Log10 too, where
+    // a fixed raw spacing covers a different pixel width depending on where it sits
+    fn bar_half_width(&self, center_x: f64, spacing_x: f64) -> usize {
+        let half_spacing = spacing_x / 2f64;
+        let low = match self.scale_x {
+            Scale::Linear => center_x - half_spacing,
+            Scale::Log10 => (center_x - half_spacing).max(f64::MIN_POSITIVE),
+        };
+        let left = self.map_x(low);
+        let right = self.map_x(center_x + half_spacing);
+        let width_px = if right > left { right - left } else { left - right };
+        let bar_width = if width_px > BAR_GAP + 1 {
+            width_px - BAR_GAP
+        } else {
+            1
+        };
+        ((bar_width - 1) / 2).max(1)
+    }
+
+    fn bars_from_points(&self, bars: &[(DisplayPoint, usize)]) -> Vec<DisplayPoint> {
+        let baseline = self.baseline_y();
+        let mut pixels = vec![];
+        for &(p, half_width) in bars {
+            let x0 = p.x.saturating_sub(half_width);
+            let x1 = (p.x + half_width).min(self.width - 1);
+            let (top, bottom) = if p.y <= baseline {
+                (p.y, baseline)
+            } else {
+                (baseline, p.y)
+            };
+            for x in x0..=x1 {
+                for y in top..=bottom {
+                    pixels.push(DisplayPoint { x: x, y: y });
+                }
+            }
+        }
+        pixels
+    }
+
+    fn bar_points<'a, T, P>(&self, serie: &Serie<'a, T, P>) -> Vec<DisplayPoint>
+        where T: Iterator<Item = P> + Clone,
+              P: Into<Point> + PartialEq
+    {
+        let spacing = serie.min_x_spacing();
+        let bars: Vec<(DisplayPoint, usize)> = serie.iter
+            .clone()
+            .map(|p| {
+                let p = p.into();
+                let point = DisplayPoint { x: self.map_x(p.x), y: self.map_y(p.y) };
+                (point, self.bar_half_width(p.x, spacing))
+            })
+            .collect();
+        self.bars_from_points(&bars)
+    }
+
+    fn histogram_points<'a, T, P>(&self, serie: &Serie<'a, T, P>, bins: usize) -> Vec<DisplayPoint>
+        where T: Iterator<Item = P> + Clone,
+              P: Into<Point> + PartialEq
+    {
+        let bins = bins.max(1);
+        let counts = serie.histogram_counts(bins);
+        let bin_width = (serie.max_x - serie.min_x) / (bins as f64);
+
+        let bars: Vec<(DisplayPoint, usize)> = counts.iter()
+            .enumerate()
+            .map(|(i, &count)| {
+                let center_x = serie.min_x + bin_width * (i as f64 + 0.5);
+                let point = DisplayPoint {
+                    x: self.map_x(center_x),
+                    y: self.map_y(count as f64),
+                };
+                (point, self.bar_half_width(center_x, bin_width))
+            })
+            .collect();
+
+        self.bars_from_points(&bars)
+    }
+
+    fn scatter_points<'a, T, P>(&self,
+                                serie: &Serie<'a, T, P>,
+                                marker: Marker,
+                                radius: usize)
+                                -> Vec<DisplayPoint>
+        where T: Iterator<Item = P> + Clone,
+              P: Into<Point> + PartialEq
+    {
+        let points: Vec<DisplayPoint> = self.serie_to_points(serie).collect();
+        self.stamp_markers(&points, marker, radius)
+    }
+
+    fn stamp_markers(&self, points: &[DisplayPoint], marker: Marker, radius: usize) -> Vec<DisplayPoint> {
+        let stencil = marker_stencil(marker, radius);
+        let mut pixels = vec![];
+        for p in points {
+            for &(dx, dy) in &stencil {
+                let x = p.x as isize + dx;
+                let y = p.y as isize + dy;
+                if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
+                    pixels.push(DisplayPoint { x: x as usize, y: y as usize });
+                }
+            }
+        }
+        pixels
+    }
+
+    fn draw_pixels(&mut self, points: Vec<DisplayPoint>, color: (u8, u8, u8)) {
         for p in points {
             let i = p.y * self.width + p.x;
             self.pixs[i] = color;
         }
     }
+
+    fn draw_legend(&mut self, entries: &[((u8, u8, u8), &str)]) {
+        if entries.is_empty() {
+            return;
+        }
+
+        let max_label_len = entries.iter().map(|&(_, label)| label.len()).max().unwrap_or(0);
+
+        let row_height = LEGEND_CHAR_H + LEGEND_LINE_GAP;
+        let box_width = 2 * LEGEND_MARGIN + LEGEND_SWATCH + LEGEND_GAP +
+                        max_label_len * (LEGEND_CHAR_W + LEGEND_CHAR_GAP);
+        let box_height = 2 * LEGEND_MARGIN + entries.len() * row_height - LEGEND_LINE_GAP;
+
+        if box_width + RIGHT_SHIFT >= self.width || box_height + RIGHT_SHIFT >= self.height {
+            return;
+        }
+
+        let x0 = self.width - RIGHT_SHIFT - box_width;
+        let y0 = RIGHT_SHIFT;
+
+        let axis_color = self.axis_color;
+
+        for (row, &(color, label)) in entries.iter().enumerate() {
+            let y = y0 + LEGEND_MARGIN + row * row_height;
+            let x = x0 + LEGEND_MARGIN;
+
+            let swatch: Vec<DisplayPoint> = (0..LEGEND_SWATCH)
+                .flat_map(|dy| (0..LEGEND_SWATCH).map(move |dx| (dx, dy)))
+                .map(|(dx, dy)| DisplayPoint { x: x + dx, y: y + dy })
+                .collect();
+            self.draw_pixels(swatch, color);
+
+            let label_x = x + LEGEND_SWATCH + LEGEND_GAP;
+            self.draw_text(label_x, y, label, axis_color);
+        }
+    }
+
+    fn draw_text(&mut self, x: usize, y: usize, text: &str, color: (u8, u8, u8)) {
+        let mut points = vec![];
+        for (i, c) in text.chars().enumerate() {
+            let char_x = x + i * (LEGEND_CHAR_W + LEGEND_CHAR_GAP);
+            for (row, bits) in glyph(c).iter().enumerate() {
+                for col in 0..LEGEND_CHAR_W {
+                    if bits & (1 << (LEGEND_CHAR_W - 1 - col)) != 0 {
+                        points.push(DisplayPoint { x: char_x + col, y: y + row });
+                    }
+                }
+            }
+        }
+        self.draw_pixels(points, color);
+    }
+}
+
+// offsets from a point's center making up one marker glyph
+fn marker_stencil(marker: Marker, radius: usize) -> Vec<(isize, isize)> {
+    let r = radius as isize;
+    let mut points = vec![];
+    match marker {
+        Marker::Dot => {
+            for dy in -r..=r {
+                for dx in -r..=r {
+                    if dx * dx + dy * dy <= r * r {
+                        points.push((dx, dy));
+                    }
+                }
+            }
+        }
+        Marker::Square => {
+            for dy in -r..=r {
+                for dx in -r..=r {
+                    points.push((dx, dy));
+                }
+            }
+        }
+        Marker::Cross => {
+            for d in -r..=r {
+                points.push((d, 0));
+                points.push((0, d));
+            }
+        }
+        Marker::X => {
+            for d in -r..=r {
+                points.push((d, d));
+                points.push((d, -d));
+            }
+        }
+    }
+    points
+}
+
+// bitmap for one legend glyph, LEGEND_CHAR_W x LEGEND_CHAR_H, unsupported chars blank
+fn glyph(c: char) -> [u8; LEGEND_CHAR_H] {
+    match c.to_ascii_uppercase() {
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b101, 0b111, 0b111, 0b111, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b111, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        _ => [0b000, 0b000, 0b000, 0b000, 0b000],
+    }
 }
 
 #[test]
@@ -296,7 +1019,7 @@ fn not_enough_space_test() {
 #[test]
 fn not_enough_points_test() {
     let v: Vec<(f64, f64)> = vec![];
-    let result = Serie::new(v.into_iter(), "#0000ff");
+    let result = Serie::new(v.into_iter(), "#0000ff", "serie");
     assert_eq!(result.unwrap_err().to_string(),
                "There are not enough points to display on graph.");
 }
@@ -304,7 +1027,7 @@ fn not_enough_points_test() {
 #[test]
 fn one_point_test() {
     let p = vec![(1f64, 1f64)];
-    let result = Serie::new(p.into_iter(), "#0000ff");
+    let result = Serie::new(p.into_iter(), "#0000ff", "serie");
     assert_eq!(result.unwrap_err().to_string(),
                "There are not enough points to display on graph.");
 }
@@ -312,7 +1035,7 @@ fn one_point_test() {
 #[test]
 fn two_identical_point_test() {
     let p = vec![(1f64, 1f64), (1f64, 1f64)];
-    let result = Serie::new(p.into_iter(), "#0000ff");
+    let result = Serie::new(p.into_iter(), "#0000ff", "serie");
     assert_eq!(result.unwrap_err().to_string(),
                "There are only one unique point. Can't construct line.");
 }
@@ -320,14 +1043,111 @@ fn two_identical_point_test() {
 #[test]
 fn can_create_array() {
     let p = vec![(1f64, 1f64), (2f64, 2f64), (3f64, 3f64)];
-    let serie = Serie::new(p.into_iter(), "#0000ff").unwrap();
+    let serie = Serie::new(p.into_iter(), "#0000ff", "serie").unwrap();
     let mut chart = Chart::new(100, 100, "#ffffff", "#000000").unwrap();
-    let bmp = chart.create_bmp_vec(serie).unwrap();
+    let bmp = chart.render::<BmpBackend, _, _>(vec![serie]).unwrap();
     for p in bmp {
         println!("{}", p);
     }
 }
 
+#[test]
+fn log_axis_rejects_non_positive_test() {
+    let p = vec![(1f64, -1f64), (2f64, 2f64), (3f64, 3f64)];
+    let serie = Serie::new(p.into_iter(), "#0000ff", "serie").unwrap();
+    let mut chart = Chart::new(100, 100, "#ffffff", "#000000")
+        .unwrap()
+        .with_y_scale(Scale::Log10);
+    let result = chart.render::<BmpBackend, _, _>(vec![serie]);
+    assert_eq!(result.unwrap_err().to_string(),
+               "Logarithmic axes require values strictly greater than zero.");
+}
+
+#[test]
+fn render_log_scaled_bar_chart_keeps_bars_narrow_test() {
+    let p: Vec<(f64, f64)> = (1..100).map(|i| (i as f64 * 10f64, 5f64)).collect();
+    let serie = Serie::new(p.into_iter(), "#ff0000", "serie")
+        .unwrap()
+        .with_kind(ChartKind::Bar);
+    let width = 700;
+    let height = 200;
+    let mut chart = Chart::new(width, height, "#ffffff", "#000000")
+        .unwrap()
+        .with_x_scale(Scale::Log10);
+    chart.render::<BmpBackend, _, _>(vec![serie]).unwrap();
+
+    let bar_color = (255, 0, 0);
+    let painted_columns = (0..width)
+        .filter(|&x| (0..height).any(|y| chart.pixs[y * width + x] == bar_color))
+        .count();
+    assert!(painted_columns < width / 2);
+}
+
+#[test]
+fn braille_cell_char_test() {
+    let mut backend = BrailleBackend::new(2, 4);
+    backend.set_background((255, 255, 255));
+    backend.set_axis_color((0, 0, 0));
+    backend.set_pixel(0, 0, (0, 0, 255));
+    backend.set_pixel(1, 3, (0, 0, 255));
+    assert_eq!(backend.cell_char(0, 0),
+               char::from_u32(0x2800 + 0b1000_0001).unwrap());
+}
+
+#[test]
+fn histogram_bin_edges_test() {
+    let p = vec![(0f64, 0f64), (1f64, 0f64), (2f64, 0f64), (3f64, 0f64)];
+    let serie = Serie::new(p.into_iter(), "#0000ff", "serie")
+        .unwrap()
+        .with_kind(ChartKind::Histogram { bins: 2 });
+    assert_eq!(serie.histogram_counts(2), vec![2, 2]);
+}
+
+#[test]
+fn render_histogram_paints_bars_within_plot_area_test() {
+    let p = vec![(0f64, 0f64), (1f64, 0f64), (2f64, 0f64), (9f64, 0f64), (10f64, 0f64)];
+    let serie = Serie::new(p.into_iter(), "#ff0000", "serie")
+        .unwrap()
+        .with_kind(ChartKind::Histogram { bins: 2 });
+    let width = 100;
+    let height = 100;
+    let mut chart = Chart::new(width, height, "#ffffff", "#000000").unwrap();
+    chart.render::<BmpBackend, _, _>(vec![serie]).unwrap();
+
+    let bar_color = (255, 0, 0);
+    let painted_xs: Vec<usize> = (0..width)
+        .filter(|&x| (0..height).any(|y| chart.pixs[y * width + x] == bar_color))
+        .collect();
+    assert!(!painted_xs.is_empty());
+    assert!(painted_xs.iter().all(|&x| x < width - 1));
+    assert!(painted_xs.len() < width / 2);
+}
+
+#[test]
+fn png_backend_header_test() {
+    let mut backend = PngBackend::new(4, 4);
+    backend.set_pixel(0, 0, (255, 0, 0));
+    let bytes = backend.encode();
+    assert_eq!(&bytes[0..8], &[0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a]);
+}
+
+#[test]
+fn legend_skipped_when_box_does_not_fit_test() {
+    let mut chart = Chart::new(24, 24, "#ffffff", "#000000").unwrap();
+    chart.draw_legend(&[((0, 0, 255), "VERY LONG LABEL")]);
+    assert!(chart.pixs.iter().all(|&p| p == chart.background_color));
+}
+
+#[test]
+fn marker_stencil_cross_test() {
+    let points = marker_stencil(Marker::Cross, 1);
+    assert_eq!(points.len(), 6);
+    assert!(points.contains(&(-1, 0)));
+    assert!(points.contains(&(1, 0)));
+    assert!(points.contains(&(0, -1)));
+    assert!(points.contains(&(0, 1)));
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -337,9 +1157,9 @@ mod tests {
     fn create_graph_2_points(b: &mut Bencher) {
         b.iter(|| {
             let p = vec![(1f64, 1f64), (2f64, 2f64), (3f64, 3f64)];
-            let serie = Serie::new(p.into_iter(), "#0000ff").unwrap();
+            let serie = Serie::new(p.into_iter(), "#0000ff", "serie").unwrap();
             let mut chart = Chart::new(740, 480, "#ffffff", "#000000").unwrap();
-            let _ = chart.create_bmp_vec(serie).unwrap();
+            let _ = chart.render::<BmpBackend, _, _>(vec![serie]).unwrap();
         })
     }
 
@@ -347,9 +1167,9 @@ mod tests {
     fn create_graph_1000_points(b: &mut Bencher) {
         b.iter(|| {
             let p: Vec<_> = formula!(y(x): f64 = {x*x}, x = [0f64, 1000f64; 1f64]).collect();
-            let serie = Serie::new(p.into_iter(), "#0000ff").unwrap();
+            let serie = Serie::new(p.into_iter(), "#0000ff", "serie").unwrap();
             let mut chart = Chart::new(740, 480, "#ffffff", "#000000").unwrap();
-            let _ = chart.create_bmp_vec(serie).unwrap();
+            let _ = chart.render::<BmpBackend, _, _>(vec![serie]).unwrap();
         })
     }
 
@@ -358,9 +1178,9 @@ mod tests {
     fn create_graph_1000000_points(b: &mut Bencher) {
         b.iter(|| {
             let p: Vec<_> = formula!(y(x): f64 = {x*x}, x = [0f64, 1000f64; 0.001f64]).collect();
-            let serie = Serie::new(p.into_iter(), "#0000ff").unwrap();
+            let serie = Serie::new(p.into_iter(), "#0000ff", "serie").unwrap();
             let mut chart = Chart::new(740, 480, "#ffffff", "#000000").unwrap();
-            let _ = chart.create_bmp_vec(serie).unwrap();
+            let _ = chart.render::<BmpBackend, _, _>(vec![serie]).unwrap();
         })
     }
 }
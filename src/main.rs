@@ -1,6 +1,6 @@
 #[macro_use]
 extern crate simple_graph;
-use simple_graph::graph;
+use simple_graph::graph::{BmpBackend, Chart, ChartKind, Marker, Serie};
 use std::io::prelude::*;
 use std::fs::File;
 
@@ -19,13 +19,14 @@ fn main() {
                  (9f64, -74.862),
                  (10f64, -75.592)];
 
+    let serie = Serie::new(v.into_iter(), "#0000ff", "measurements")
+        .unwrap()
+        .with_kind(ChartKind::Scatter { marker: Marker::Cross, radius: 2 });
 
+    let mut chart = Chart::new(740, 480, "#ffffff", "#000000").unwrap();
 
-    let bmp = graph::create(v.into_iter(), 740, 480).unwrap();
+    let bmp = chart.render::<BmpBackend, _, _>(vec![serie]).unwrap();
 
     let mut file = File::create("graph.bmp").unwrap();
     file.write_all(&bmp).unwrap();
-
-
-
 }